@@ -0,0 +1,156 @@
+//! An axis-aligned bounding box, in the spirit of cgmath's `Aabb2`/`Aabb3`.
+#![allow(dead_code)]
+use crate::{scalar::Scalar, vec2::Vec2, vec3::Vec3};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb2<T: Scalar> {
+    pub min: Vec2<T>,
+    pub max: Vec2<T>,
+}
+
+impl<T: Scalar> Aabb2<T> {
+    pub fn new(min: Vec2<T>, max: Vec2<T>) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box containing `points`, or `None` if it's empty.
+    pub fn from_points(mut points: impl Iterator<Item = Vec2<T>>) -> Option<Self> {
+        let first = points.next()?;
+        let mut aabb = Self::new(first, first);
+        for p in points {
+            aabb = aabb.grow(p);
+        }
+        Some(aabb)
+    }
+
+    /// Grow the box to include `point`, if it doesn't already.
+    pub fn grow(self, point: Vec2<T>) -> Self {
+        Self {
+            min: Vec2 {
+                x: if point.x < self.min.x {
+                    point.x
+                } else {
+                    self.min.x
+                },
+                y: if point.y < self.min.y {
+                    point.y
+                } else {
+                    self.min.y
+                },
+            },
+            max: Vec2 {
+                x: if point.x > self.max.x {
+                    point.x
+                } else {
+                    self.max.x
+                },
+                y: if point.y > self.max.y {
+                    point.y
+                } else {
+                    self.max.y
+                },
+            },
+        }
+    }
+
+    /// Smallest box containing both `self` and `other`.
+    pub fn union(self, other: Self) -> Self {
+        self.grow(other.min).grow(other.max)
+    }
+
+    pub fn contains(&self, point: Vec2<T>) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+
+    /// Whether `self` and `other` overlap (touching counts as overlapping).
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
+    /// Squared Euclidean distance from `point` to the closest point of the
+    /// box; zero if `point` lies inside.
+    pub fn distance_sq_to(&self, point: Vec2<T>) -> T {
+        let dx = Self::axis_gap(point.x, self.min.x, self.max.x);
+        let dy = Self::axis_gap(point.y, self.min.y, self.max.y);
+        dx * dx + dy * dy
+    }
+
+    fn axis_gap(v: T, lo: T, hi: T) -> T {
+        if v < lo {
+            lo - v
+        } else if v > hi {
+            v - hi
+        } else {
+            T::default()
+        }
+    }
+}
+
+/// The 3D analogue of [`Aabb2`], used to bound [`crate::oct::OctNode`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb3<T: Scalar> {
+    pub min: Vec3<T>,
+    pub max: Vec3<T>,
+}
+
+impl<T: Scalar> Aabb3<T> {
+    pub fn new(min: Vec3<T>, max: Vec3<T>) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box containing `points`, or `None` if it's empty.
+    pub fn from_points(mut points: impl Iterator<Item = Vec3<T>>) -> Option<Self> {
+        let first = points.next()?;
+        let mut aabb = Self::new(first, first);
+        for p in points {
+            aabb = aabb.grow(p);
+        }
+        Some(aabb)
+    }
+
+    /// Grow the box to include `point`, if it doesn't already.
+    pub fn grow(self, point: Vec3<T>) -> Self {
+        Self {
+            min: Vec3 {
+                x: if point.x < self.min.x {
+                    point.x
+                } else {
+                    self.min.x
+                },
+                y: if point.y < self.min.y {
+                    point.y
+                } else {
+                    self.min.y
+                },
+                z: if point.z < self.min.z {
+                    point.z
+                } else {
+                    self.min.z
+                },
+            },
+            max: Vec3 {
+                x: if point.x > self.max.x {
+                    point.x
+                } else {
+                    self.max.x
+                },
+                y: if point.y > self.max.y {
+                    point.y
+                } else {
+                    self.max.y
+                },
+                z: if point.z > self.max.z {
+                    point.z
+                } else {
+                    self.max.z
+                },
+            },
+        }
+    }
+}
@@ -1,34 +1,61 @@
+mod aabb;
 mod mat2;
+mod mat3;
+mod oct;
+mod scalar;
 mod utils;
 mod vec2;
+mod vec3;
+use aabb::Aabb2;
 use mat2::Mat2;
-use numpy::{ndarray::Array1, PyArray1, PyArray2, PyReadonlyArrayDyn};
+use numpy::{PyArray1, PyArray2, PyArray3, PyReadonlyArrayDyn};
 use pyo3::{exceptions::PyValueError, prelude::*};
+// `rayon` is an optional dependency enabled by the `rayon` feature; both are
+// declared in Cargo.toml, which lives outside this source tree.
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use scalar::Scalar;
 use utils::to_pos_array;
 use vec2::Vec2;
 
-use crate::utils::check_pos_array;
+use crate::utils::{check_pos_array, to_tensor_array};
 
 /// default value for θ. nodes with width/r<θ are considered far enough away to
 /// use the approximate potential.
-const DEFAULT_ACC: F = 0.3;
-const G: F = -1.;
+const DEFAULT_ACC: f64 = 0.3;
+
+/// Below this many points, `make_node` builds its four children serially
+/// rather than paying rayon's task-spawning overhead.
+#[cfg(feature = "rayon")]
+const RAYON_NODE_THRESHOLD: usize = 1024;
 
 /// A Python module implemented in Rust.
 #[pymodule]
 fn fastgravity(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_class::<GravitySystem>()?;
+    m.add_class::<GravitySystemF32>()?;
+    m.add_class::<GravitySystemF64>()?;
+    oct::register(m)?;
     Ok(())
 }
 
-#[pyclass]
-struct GravitySystem {
-    root: QuadNode,
+/// The non-pyo3 part of `GravitySystem`, generic over the scalar type so the
+/// quadtree logic below is written once for both `f32` and `f64`.
+struct GravitySystemImpl<T: Scalar> {
+    root: QuadNode<T>,
+    /// Plummer softening length: `potential_at`/`gravity_at` replace `dist`
+    /// with `sqrt(dist^2 + epsilon^2)`, so the field stays finite at
+    /// near-coincident points instead of blowing up as `dist -> 0`. With the
+    /// default `epsilon = 0`, exactly coincident points (e.g. a body queried
+    /// at its own position) still fall back to the old, unsoftened `0`
+    /// rather than producing `inf`/`NaN`.
+    epsilon: T,
 }
-#[pymethods]
-impl GravitySystem {
-    #[new]
-    fn py_new(positions: PyReadonlyArrayDyn<F>, masses: PyReadonlyArrayDyn<F>) -> PyResult<Self> {
+impl<T: Scalar> GravitySystemImpl<T> {
+    fn new(
+        positions: &PyReadonlyArrayDyn<T>,
+        masses: &PyReadonlyArrayDyn<T>,
+        epsilon: T,
+    ) -> PyResult<Self> {
         let n = *positions.shape().first().unwrap_or(&0);
         if n != *masses.shape().first().unwrap_or(&0) {
             return Err(PyValueError::new_err(format!(
@@ -50,103 +77,279 @@ impl GravitySystem {
         let masses = masses.as_array();
         let vecs = check_pos_array(&positions)?;
         let pts = vecs
+            .enumerate()
             .zip(masses.iter())
-            .map(|(pos, m)| Body { pos, mass: *m })
+            .map(|((idx, pos), m)| Body { pos, mass: *m, idx })
             .collect();
         Ok(Self {
             root: tree_from_points(pts),
+            epsilon,
         })
     }
 
-    #[pyo3(signature = (at_pos, use_quad=true, accuracy=DEFAULT_ACC))]
-    fn evaluate_potential<'py>(
-        &self,
-        py: Python<'py>,
-        at_pos: PyReadonlyArrayDyn<F>,
-        use_quad: bool,
-        accuracy: F,
-    ) -> PyResult<&'py PyArray1<F>> {
-        // TODO: rayon?
-        let arr = at_pos.as_array();
-        let vecs = check_pos_array(&arr)?;
-        Ok(PyArray1::from_owned_array(
-            py,
-            Array1::from_vec(
-                vecs.map(|v| self.root.potential_at(v, use_quad, accuracy))
-                    .collect(),
-            ),
-        ))
-    }
-
-    #[pyo3(signature = (at_pos, use_quad=true, accuracy=DEFAULT_ACC))]
-    fn evaluate_gravity<'py>(
-        &self,
-        py: Python<'py>,
-        at_pos: PyReadonlyArrayDyn<F>,
-        use_quad: bool,
-        accuracy: F,
-    ) -> PyResult<&'py PyArray2<F>> {
-        // TODO: rayon?
-        let arr = at_pos.as_array();
-        let vecs = check_pos_array(&arr)?;
-        Ok(to_pos_array(
-            py,
-            vecs.map(|v| self.root.gravity_at(v, use_quad, accuracy)),
-        ))
+    fn potential_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T) -> T {
+        self.root
+            .potential_at(pos, use_quad, accuracy, self.epsilon)
+    }
+
+    fn gravity_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T) -> Vec2<T> {
+        self.root.gravity_at(pos, use_quad, accuracy, self.epsilon)
+    }
+
+    /// The Hessian of the potential (the tidal tensor) at `pos`.
+    fn tidal_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T) -> Mat2<T> {
+        self.root.tidal_at(pos, use_quad, accuracy)
+    }
+
+    /// Indices of bodies whose position lies within the box `[lo, hi]`.
+    fn bodies_in_box(&self, lo: Vec2<T>, hi: Vec2<T>) -> Vec<i64> {
+        let query = Aabb2::new(lo, hi);
+        let mut out = Vec::new();
+        self.root.collect_in_box(&query, &mut out);
+        out
+    }
+
+    /// Indices of bodies within `radius` of `center`.
+    fn bodies_within_radius(&self, center: Vec2<T>, radius: T) -> Vec<i64> {
+        let mut out = Vec::new();
+        self.root.collect_within_radius(center, radius, &mut out);
+        out
+    }
+}
+
+/// Evaluates the potential at every one of `vecs`, over a rayon `par_iter`
+/// when the `rayon` feature is on, else a plain sequential iterator.
+fn eval_potential_parallel<T: Scalar>(
+    sys: &GravitySystemImpl<T>,
+    vecs: &[Vec2<T>],
+    use_quad: bool,
+    accuracy: T,
+) -> Vec<T> {
+    #[cfg(feature = "rayon")]
+    {
+        vecs.par_iter()
+            .map(|&v| sys.potential_at(v, use_quad, accuracy))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        vecs.iter()
+            .map(|&v| sys.potential_at(v, use_quad, accuracy))
+            .collect()
+    }
+}
+
+/// Evaluates gravity at every one of `vecs`; see [`eval_potential_parallel`].
+fn eval_gravity_parallel<T: Scalar>(
+    sys: &GravitySystemImpl<T>,
+    vecs: &[Vec2<T>],
+    use_quad: bool,
+    accuracy: T,
+) -> Vec<Vec2<T>> {
+    #[cfg(feature = "rayon")]
+    {
+        vecs.par_iter()
+            .map(|&v| sys.gravity_at(v, use_quad, accuracy))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        vecs.iter()
+            .map(|&v| sys.gravity_at(v, use_quad, accuracy))
+            .collect()
+    }
+}
+
+/// Evaluates the tidal tensor at every one of `vecs`; see
+/// [`eval_potential_parallel`].
+fn eval_tidal_parallel<T: Scalar>(
+    sys: &GravitySystemImpl<T>,
+    vecs: &[Vec2<T>],
+    use_quad: bool,
+    accuracy: T,
+) -> Vec<Mat2<T>> {
+    #[cfg(feature = "rayon")]
+    {
+        vecs.par_iter()
+            .map(|&v| sys.tidal_at(v, use_quad, accuracy))
+            .collect()
     }
+    #[cfg(not(feature = "rayon"))]
+    {
+        vecs.iter()
+            .map(|&v| sys.tidal_at(v, use_quad, accuracy))
+            .collect()
+    }
+}
+
+/// Defines a pyclass wrapping a [`GravitySystemImpl`] for one concrete float
+/// type, so that Python users can pick `GravitySystemF32` to halve memory use
+/// on large single-precision datasets, or `GravitySystemF64` for the original
+/// precision.
+macro_rules! impl_gravity_system_pyclass {
+    ($name:ident, $float:ty) => {
+        #[pyclass]
+        struct $name {
+            inner: GravitySystemImpl<$float>,
+        }
+        #[pymethods]
+        impl $name {
+            /// `epsilon` is a Plummer softening length added in quadrature to
+            /// `dist` in the force/potential formulas, keeping them finite
+            /// for near-coincident points. It defaults to `0`, under which
+            /// querying exactly at a body's own position still yields the
+            /// old finite `0` rather than `inf`/`NaN` — pass a positive
+            /// `epsilon` to get smooth, non-singular self-gravity instead.
+            #[new]
+            #[pyo3(signature = (positions, masses, epsilon=0. as $float))]
+            fn py_new(
+                positions: PyReadonlyArrayDyn<$float>,
+                masses: PyReadonlyArrayDyn<$float>,
+                epsilon: $float,
+            ) -> PyResult<Self> {
+                Ok(Self {
+                    inner: GravitySystemImpl::new(&positions, &masses, epsilon)?,
+                })
+            }
+
+            #[pyo3(signature = (at_pos, use_quad=true, accuracy=DEFAULT_ACC as $float))]
+            fn evaluate_potential<'py>(
+                &self,
+                py: Python<'py>,
+                at_pos: PyReadonlyArrayDyn<$float>,
+                use_quad: bool,
+                accuracy: $float,
+            ) -> PyResult<&'py PyArray1<$float>> {
+                let arr = at_pos.as_array();
+                let vecs: Vec<_> = check_pos_array(&arr)?.collect();
+                let result = py.allow_threads(|| {
+                    eval_potential_parallel(&self.inner, &vecs, use_quad, accuracy)
+                });
+                Ok(PyArray1::from_vec(py, result))
+            }
+
+            #[pyo3(signature = (at_pos, use_quad=true, accuracy=DEFAULT_ACC as $float))]
+            fn evaluate_gravity<'py>(
+                &self,
+                py: Python<'py>,
+                at_pos: PyReadonlyArrayDyn<$float>,
+                use_quad: bool,
+                accuracy: $float,
+            ) -> PyResult<&'py PyArray2<$float>> {
+                let arr = at_pos.as_array();
+                let vecs: Vec<_> = check_pos_array(&arr)?.collect();
+                let result = py.allow_threads(|| {
+                    eval_gravity_parallel(&self.inner, &vecs, use_quad, accuracy)
+                });
+                Ok(to_pos_array(py, result.into_iter()))
+            }
+
+            #[pyo3(signature = (at_pos, use_quad=true, accuracy=DEFAULT_ACC as $float))]
+            fn evaluate_tidal_tensor<'py>(
+                &self,
+                py: Python<'py>,
+                at_pos: PyReadonlyArrayDyn<$float>,
+                use_quad: bool,
+                accuracy: $float,
+            ) -> PyResult<&'py PyArray3<$float>> {
+                let arr = at_pos.as_array();
+                let vecs: Vec<_> = check_pos_array(&arr)?.collect();
+                let result = py
+                    .allow_threads(|| eval_tidal_parallel(&self.inner, &vecs, use_quad, accuracy));
+                Ok(to_tensor_array(py, result.into_iter()))
+            }
+
+            /// Indices of bodies whose position lies within the axis-aligned
+            /// box `[lo, hi]`.
+            fn bodies_in_box<'py>(
+                &self,
+                py: Python<'py>,
+                lo: ($float, $float),
+                hi: ($float, $float),
+            ) -> &'py PyArray1<i64> {
+                PyArray1::from_vec(
+                    py,
+                    self.inner
+                        .bodies_in_box(Vec2::new(lo.0, lo.1), Vec2::new(hi.0, hi.1)),
+                )
+            }
+
+            /// Indices of bodies within `r` of `center`.
+            fn bodies_within_radius<'py>(
+                &self,
+                py: Python<'py>,
+                center: ($float, $float),
+                r: $float,
+            ) -> &'py PyArray1<i64> {
+                PyArray1::from_vec(
+                    py,
+                    self.inner
+                        .bodies_within_radius(Vec2::new(center.0, center.1), r),
+                )
+            }
+        }
+    };
 }
 
-type F = f64;
+impl_gravity_system_pyclass!(GravitySystemF32, f32);
+impl_gravity_system_pyclass!(GravitySystemF64, f64);
 
 #[derive(Clone, Copy)]
-struct Body {
-    mass: F,
-    pos: Vec2<F>,
+struct Body<T: Scalar> {
+    mass: T,
+    pos: Vec2<T>,
+    /// Index into the original `positions`/`masses` arrays, for spatial
+    /// queries that need to report which bodies matched.
+    idx: usize,
 }
-trait Quad: Sized {
-    fn com(&self) -> (F, Vec2<F>);
-    fn quadrupole(&self) -> Mat2<F>;
-    fn potential_at(&self, pos: Vec2<F>, _use_quad: bool, accuracy: F) -> F;
-    fn gravity_at(&self, pos: Vec2<F>, _use_quad: bool, accuracy: F) -> Vec2<F>;
+trait Quad<T: Scalar>: Sized {
+    fn com(&self) -> (T, Vec2<T>);
+    fn quadrupole(&self) -> Mat2<T>;
+    /// `epsilon` is the Plummer softening length: `dist` is replaced with
+    /// `sqrt(dist^2 + epsilon^2)`, so the potential stays finite as `dist ->
+    /// 0`.
+    fn potential_at(&self, pos: Vec2<T>, _use_quad: bool, accuracy: T, epsilon: T) -> T;
+    /// See [`Quad::potential_at`] for `epsilon`.
+    fn gravity_at(&self, pos: Vec2<T>, _use_quad: bool, accuracy: T, epsilon: T) -> Vec2<T>;
+    /// The Hessian of the potential at `pos`, i.e. d^2 phi / dx_i dx_j.
+    fn tidal_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T) -> Mat2<T>;
 }
 
-enum QuadNode {
-    Leaf(QuadLeaf),
-    Interior(QuadInterior),
+enum QuadNode<T: Scalar> {
+    Leaf(QuadLeaf<T>),
+    Interior(QuadInterior<T>),
 }
 
-struct QuadLeaf {
-    body: Body,
+struct QuadLeaf<T: Scalar> {
+    body: Body<T>,
 }
-struct QuadInterior {
+struct QuadInterior<T: Scalar> {
     /// yx: sw, se, nw, ne
     /// yx: --, -+, +-, ++
-    children: [Option<Box<QuadNode>>; 4],
-    com: Vec2<F>,
-    total_mass: F,
-    quadrupole: Mat2<F>,
+    children: [Option<Box<QuadNode<T>>>; 4],
+    com: Vec2<T>,
+    total_mass: T,
+    quadrupole: Mat2<T>,
 
-    extent_x: (F, F),
-    extent_y: (F, F),
+    aabb: Aabb2<T>,
 }
-impl QuadInterior {
+impl<T: Scalar> QuadInterior<T> {
     fn new(
-        sw: Option<QuadNode>,
-        se: Option<QuadNode>,
-        nw: Option<QuadNode>,
-        ne: Option<QuadNode>,
-        extent_x: (F, F),
-        extent_y: (F, F),
+        sw: Option<QuadNode<T>>,
+        se: Option<QuadNode<T>>,
+        nw: Option<QuadNode<T>>,
+        ne: Option<QuadNode<T>>,
+        aabb: Aabb2<T>,
     ) -> Self {
         let (total_mass, com) = {
-            let mut mass = 0.;
+            let mut mass = T::default();
             let mut com = Vec2::zero();
             for child in [&sw, &se, &nw, &ne].into_iter().flatten() {
                 let (child_m, child_com) = child.com();
-                mass += child_m;
+                mass = mass + child_m;
                 com = com + child_com * child_m;
             }
-            assert!(mass != 0.); // sanity check
+            assert!(mass != T::default()); // sanity check
             com = com / mass;
             (mass, com)
         };
@@ -166,26 +369,64 @@ impl QuadInterior {
             total_mass,
             com,
             quadrupole,
-            extent_x,
-            extent_y,
+            aabb,
         }
     }
 
-    fn width(&self) -> F {
-        F::hypot(
-            self.extent_x.1 - self.extent_x.0,
-            self.extent_y.1 - self.extent_y.0,
-        )
+    fn width(&self) -> T {
+        let diag = self.aabb.max - self.aabb.min;
+        T::hypot(diag.x, diag.y)
     }
 
-    fn some_children(&self) -> impl Iterator<Item = &QuadNode> {
+    fn some_children(&self) -> impl Iterator<Item = &QuadNode<T>> {
         self.children
             .iter()
             .filter_map(|x| x.as_ref().map(|n| n.as_ref()))
     }
 }
 
-fn make_node(pts: Vec<Body>, extent_x: (F, F), extent_y: (F, F)) -> Option<QuadNode> {
+impl<T: Scalar> QuadNode<T> {
+    /// Appends the indices of bodies within `query` to `out`, pruning any
+    /// subtree whose bounding box doesn't intersect it.
+    fn collect_in_box(&self, query: &Aabb2<T>, out: &mut Vec<i64>) {
+        match self {
+            QuadNode::Leaf(leaf) => {
+                if query.contains(leaf.body.pos) {
+                    out.push(leaf.body.idx as i64);
+                }
+            }
+            QuadNode::Interior(interior) => {
+                if interior.aabb.intersects(query) {
+                    for child in interior.some_children() {
+                        child.collect_in_box(query, out);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Appends the indices of bodies within `radius` of `center` to `out`,
+    /// pruning any subtree whose bounding box is entirely farther away.
+    fn collect_within_radius(&self, center: Vec2<T>, radius: T, out: &mut Vec<i64>) {
+        let radius_sq = radius * radius;
+        match self {
+            QuadNode::Leaf(leaf) => {
+                if (leaf.body.pos - center).sq_len() <= radius_sq {
+                    out.push(leaf.body.idx as i64);
+                }
+            }
+            QuadNode::Interior(interior) => {
+                if interior.aabb.distance_sq_to(center) <= radius_sq {
+                    for child in interior.some_children() {
+                        child.collect_within_radius(center, radius, out);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn make_node<T: Scalar>(pts: Vec<Body<T>>, aabb: Aabb2<T>) -> Option<QuadNode<T>> {
     if pts.is_empty() {
         None
     } else if pts.len() == 1 {
@@ -193,66 +434,75 @@ fn make_node(pts: Vec<Body>, extent_x: (F, F), extent_y: (F, F)) -> Option<QuadN
             body: *pts.first().unwrap(),
         }))
     } else {
-        let (l, r) = extent_x;
-        let (b, t) = extent_y;
-        let div_x = (l + r) / 2.;
-        let div_y = (b + t) / 2.;
-        let sw = make_node(
-            pts.iter()
-                .copied()
-                .filter(|x| x.pos.x < div_x && x.pos.y < div_y)
-                .collect(),
-            (l, div_x),
-            (b, div_y),
-        );
-        let se = make_node(
-            pts.iter()
-                .copied()
-                .filter(|x| x.pos.x >= div_x && x.pos.y < div_y)
-                .collect(),
-            (div_x, r),
-            (b, div_y),
-        );
-        let nw = make_node(
-            pts.iter()
-                .copied()
-                .filter(|x| x.pos.x < div_x && x.pos.y >= div_y)
-                .collect(),
-            (l, div_x),
-            (div_y, t),
-        );
-        let ne = make_node(
-            pts.iter()
-                .copied()
-                .filter(|x| x.pos.x >= div_x && x.pos.y >= div_y)
-                .collect(),
-            (div_x, r),
-            (div_y, t),
+        let Vec2 { x: l, y: b } = aabb.min;
+        let Vec2 { x: r, y: t } = aabb.max;
+        let div_x = (l + r) * T::from_f64(0.5);
+        let div_y = (b + t) * T::from_f64(0.5);
+
+        let sw_pts: Vec<_> = pts
+            .iter()
+            .copied()
+            .filter(|x| x.pos.x < div_x && x.pos.y < div_y)
+            .collect();
+        let se_pts: Vec<_> = pts
+            .iter()
+            .copied()
+            .filter(|x| x.pos.x >= div_x && x.pos.y < div_y)
+            .collect();
+        let nw_pts: Vec<_> = pts
+            .iter()
+            .copied()
+            .filter(|x| x.pos.x < div_x && x.pos.y >= div_y)
+            .collect();
+        let ne_pts: Vec<_> = pts
+            .iter()
+            .copied()
+            .filter(|x| x.pos.x >= div_x && x.pos.y >= div_y)
+            .collect();
+
+        let sw_aabb = Aabb2::new(Vec2::new(l, b), Vec2::new(div_x, div_y));
+        let se_aabb = Aabb2::new(Vec2::new(div_x, b), Vec2::new(r, div_y));
+        let nw_aabb = Aabb2::new(Vec2::new(l, div_y), Vec2::new(div_x, t));
+        let ne_aabb = Aabb2::new(Vec2::new(div_x, div_y), Vec2::new(r, t));
+
+        #[cfg(feature = "rayon")]
+        let (sw, se, nw, ne) = if pts.len() >= RAYON_NODE_THRESHOLD {
+            let ((sw, se), (nw, ne)) = rayon::join(
+                || rayon::join(|| make_node(sw_pts, sw_aabb), || make_node(se_pts, se_aabb)),
+                || rayon::join(|| make_node(nw_pts, nw_aabb), || make_node(ne_pts, ne_aabb)),
+            );
+            (sw, se, nw, ne)
+        } else {
+            (
+                make_node(sw_pts, sw_aabb),
+                make_node(se_pts, se_aabb),
+                make_node(nw_pts, nw_aabb),
+                make_node(ne_pts, ne_aabb),
+            )
+        };
+        #[cfg(not(feature = "rayon"))]
+        let (sw, se, nw, ne) = (
+            make_node(sw_pts, sw_aabb),
+            make_node(se_pts, se_aabb),
+            make_node(nw_pts, nw_aabb),
+            make_node(ne_pts, ne_aabb),
         );
-        Some(QuadNode::Interior(QuadInterior::new(
-            sw, se, nw, ne, extent_x, extent_y,
-        )))
+
+        Some(QuadNode::Interior(QuadInterior::new(sw, se, nw, ne, aabb)))
     }
 }
 
-fn tree_from_points(pts: Vec<Body>) -> QuadNode {
+fn tree_from_points<T: Scalar>(pts: Vec<Body<T>>) -> QuadNode<T> {
     assert!(!pts.is_empty());
-    let extent_x = (
-        pts.iter().map(|b| b.pos.x).min_by(F::total_cmp).unwrap(),
-        pts.iter().map(|b| b.pos.x).max_by(F::total_cmp).unwrap(),
-    );
-    let extent_y = (
-        pts.iter().map(|b| b.pos.y).min_by(F::total_cmp).unwrap(),
-        pts.iter().map(|b| b.pos.y).max_by(F::total_cmp).unwrap(),
-    );
-    make_node(pts, extent_x, extent_y).unwrap()
+    let aabb = Aabb2::from_points(pts.iter().map(|b| b.pos)).unwrap();
+    make_node(pts, aabb).unwrap()
 }
 
 /// Computes Q_{αβ} = 2 r_α r_β - δ_{αβ} r^2
 /// It's traceless and symmetric, so has only 2 independent elements
-fn to_quadrup_tensor(r: Vec2<F>) -> Mat2<F> {
+fn to_quadrup_tensor<T: Scalar>(r: Vec2<T>) -> Mat2<T> {
     let diag = r.x * r.x - r.y * r.y;
-    let cross = 2. * r.x * r.y;
+    let cross = T::from_f64(2.) * r.x * r.y;
     Mat2 {
         xx: diag,
         yy: -diag,
@@ -261,116 +511,283 @@ fn to_quadrup_tensor(r: Vec2<F>) -> Mat2<F> {
     }
 }
 
-impl Quad for QuadLeaf {
-    fn com(&self) -> (F, Vec2<F>) {
+/// The monopole term of the tidal tensor (the Hessian of `G*mass/dist`):
+/// `G*mass*(3*e(x)e(y) - I)/dist^3`, where `e = r/dist`.
+fn monopole_tidal<T: Scalar>(mass: T, r: Vec2<T>, dist: T) -> Mat2<T> {
+    let e = r / dist;
+    let three = T::from_f64(3.);
+    let scale = T::G * mass / dist.powi(3);
+    Mat2 {
+        xx: scale * (three * e.x * e.x - T::from_f64(1.)),
+        yy: scale * (three * e.y * e.y - T::from_f64(1.)),
+        xy: scale * three * e.x * e.y,
+        yx: scale * three * e.x * e.y,
+    }
+}
+
+/// The quadrupole correction to the tidal tensor: the Hessian of the
+/// quadrupole potential term `G*quad.eval_quadratic(e)/(2*dist^3)`, obtained
+/// by differentiating it twice with respect to the query position.
+fn quadrupole_tidal<T: Scalar>(quad: Mat2<T>, r: Vec2<T>, dist: T) -> Mat2<T> {
+    let e = r / dist;
+    let qe = quad.matmul(e);
+    let qee = quad.eval_quadratic(e);
+    let five = T::from_f64(5.);
+    let scale = T::G / dist.powi(5);
+    Mat2 {
+        xx: scale
+            * (quad.xx - T::from_f64(10.) * qe.x * e.x - T::from_f64(2.5) * qee
+                + T::from_f64(17.5) * qee * e.x * e.x),
+        yy: scale
+            * (quad.yy - T::from_f64(10.) * qe.y * e.y - T::from_f64(2.5) * qee
+                + T::from_f64(17.5) * qee * e.y * e.y),
+        xy: scale
+            * (quad.xy - five * qe.x * e.y - five * qe.y * e.x
+                + T::from_f64(17.5) * qee * e.x * e.y),
+        yx: scale
+            * (quad.yx - five * qe.y * e.x - five * qe.x * e.y
+                + T::from_f64(17.5) * qee * e.y * e.x),
+    }
+}
+
+impl<T: Scalar> Quad<T> for QuadLeaf<T> {
+    fn com(&self) -> (T, Vec2<T>) {
         (self.body.mass, self.body.pos)
     }
 
-    fn quadrupole(&self) -> Mat2<F> {
+    fn quadrupole(&self) -> Mat2<T> {
         Mat2::default()
     }
 
-    fn potential_at(&self, pos: Vec2<F>, _use_quad: bool, _accuracy: F) -> F {
-        let dist = (pos - self.body.pos).norm();
-        if dist == 0. {
-            0.
+    fn potential_at(&self, pos: Vec2<T>, _use_quad: bool, _accuracy: T, epsilon: T) -> T {
+        let r = pos - self.body.pos;
+        let soft_dist = (r.sq_len() + epsilon * epsilon).sqrt();
+        if soft_dist == T::default() {
+            // unsoftened (epsilon=0) self-interaction: keep the old finite
+            // result instead of dividing by zero.
+            T::default()
         } else {
-            G * self.body.mass / dist
+            T::G * self.body.mass / soft_dist
         }
     }
 
-    fn gravity_at(&self, pos: Vec2<F>, _use_quad: bool, _accuracy: F) -> Vec2<F> {
+    fn gravity_at(&self, pos: Vec2<T>, _use_quad: bool, _accuracy: T, epsilon: T) -> Vec2<T> {
         let r = pos - self.body.pos;
-        let dist = r.norm();
-        if dist == 0. {
+        let soft_dist = (r.sq_len() + epsilon * epsilon).sqrt();
+        if soft_dist == T::default() {
+            // unsoftened (epsilon=0) self-interaction: keep the old finite
+            // result instead of dividing by zero.
             Default::default()
         } else {
-            let e = r / dist;
-            e * (G * self.body.mass / dist.powi(2))
+            r * (T::G * self.body.mass / soft_dist.powi(3))
+        }
+    }
+
+    fn tidal_at(&self, pos: Vec2<T>, _use_quad: bool, _accuracy: T) -> Mat2<T> {
+        let r = pos - self.body.pos;
+        let dist = r.norm();
+        if dist == T::default() {
+            Mat2::default()
+        } else {
+            monopole_tidal(self.body.mass, r, dist)
         }
     }
 }
 
-impl Quad for QuadInterior {
-    fn com(&self) -> (F, Vec2<F>) {
+impl<T: Scalar> Quad<T> for QuadInterior<T> {
+    fn com(&self) -> (T, Vec2<T>) {
         (self.total_mass, self.com)
     }
 
-    fn quadrupole(&self) -> Mat2<F> {
+    fn quadrupole(&self) -> Mat2<T> {
         self.quadrupole
     }
 
-    fn potential_at(&self, pos: Vec2<F>, use_quad: bool, accuracy: F) -> F {
+    fn potential_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T, epsilon: T) -> T {
         let (mass, com) = self.com();
         let r = pos - com;
         let dist = r.norm();
-        if dist > 0. && self.width() / dist < accuracy {
-            let scalar_part = mass / dist;
+        if dist > T::default() && self.width() / dist < accuracy {
+            let soft_dist = (r.sq_len() + epsilon * epsilon).sqrt();
+            let scalar_part = mass / soft_dist;
             let mut total = scalar_part;
             if use_quad {
                 let e = r / dist;
-                let quadrupole_part = self.quadrupole().eval_quadratic(e) / (2. * dist.powi(3));
-                total += quadrupole_part;
+                let quadrupole_part =
+                    self.quadrupole().eval_quadratic(e) / (T::from_f64(2.) * soft_dist.powi(3));
+                total = total + quadrupole_part;
             }
-            G * total
+            T::G * total
         } else {
             // exact calculation
             self.some_children()
-                .map(|x| x.potential_at(pos, use_quad, accuracy))
-                .sum::<F>()
+                .map(|x| x.potential_at(pos, use_quad, accuracy, epsilon))
+                .sum::<T>()
         }
     }
 
-    fn gravity_at(&self, pos: Vec2<F>, use_quad: bool, accuracy: F) -> Vec2<F> {
+    fn gravity_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T, epsilon: T) -> Vec2<T> {
         let (mass, com) = self.com();
         let r = pos - com;
         let dist = r.norm();
-        if dist > 0. && self.width() / dist < accuracy {
-            let e = r / dist;
-            let scalar_part = e * (mass / dist.powi(2));
+        if dist > T::default() && self.width() / dist < accuracy {
+            let soft_dist = (r.sq_len() + epsilon * epsilon).sqrt();
+            let scalar_part = r * (mass / soft_dist.powi(3));
             let mut total = scalar_part;
             if use_quad {
-                let dist4 = dist.powi(4);
-                let quadrupole_part_1 = e * (self.quadrupole().eval_quadratic(e) * 2.5 / dist4);
+                let e = r / dist;
+                let dist4 = soft_dist.powi(4);
+                let quadrupole_part_1 =
+                    e * (self.quadrupole().eval_quadratic(e) * T::from_f64(2.5) / dist4);
                 let quadrupole_part_2 = -self.quadrupole().matmul(e) / dist4;
                 total = total + quadrupole_part_1 + quadrupole_part_2;
             }
-            total * G
+            total * T::G
+        } else {
+            // exact calculation
+            self.some_children()
+                .map(|x| x.gravity_at(pos, use_quad, accuracy, epsilon))
+                .sum()
+        }
+    }
+
+    fn tidal_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T) -> Mat2<T> {
+        let (mass, com) = self.com();
+        let r = pos - com;
+        let dist = r.norm();
+        if dist > T::default() && self.width() / dist < accuracy {
+            let mut total = monopole_tidal(mass, r, dist);
+            if use_quad {
+                total = total + quadrupole_tidal(self.quadrupole(), r, dist);
+            }
+            total
         } else {
             // exact calculation
             self.some_children()
-                .map(|x| x.gravity_at(pos, use_quad, accuracy))
+                .map(|x| x.tidal_at(pos, use_quad, accuracy))
                 .sum()
         }
     }
 }
 
-impl Quad for QuadNode {
-    fn com(&self) -> (F, Vec2<F>) {
+impl<T: Scalar> Quad<T> for QuadNode<T> {
+    fn com(&self) -> (T, Vec2<T>) {
         match self {
             QuadNode::Leaf(x) => x.com(),
             QuadNode::Interior(x) => x.com(),
         }
     }
 
-    fn quadrupole(&self) -> Mat2<F> {
+    fn quadrupole(&self) -> Mat2<T> {
         match self {
             QuadNode::Leaf(x) => x.quadrupole(),
             QuadNode::Interior(x) => x.quadrupole(),
         }
     }
 
-    fn potential_at(&self, pos: Vec2<F>, use_quad: bool, accuracy: F) -> F {
+    fn potential_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T, epsilon: T) -> T {
         match self {
-            QuadNode::Leaf(x) => x.potential_at(pos, use_quad, accuracy),
-            QuadNode::Interior(x) => x.potential_at(pos, use_quad, accuracy),
+            QuadNode::Leaf(x) => x.potential_at(pos, use_quad, accuracy, epsilon),
+            QuadNode::Interior(x) => x.potential_at(pos, use_quad, accuracy, epsilon),
         }
     }
 
-    fn gravity_at(&self, pos: Vec2<F>, use_quad: bool, accuracy: F) -> Vec2<F> {
+    fn gravity_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T, epsilon: T) -> Vec2<T> {
         match self {
-            QuadNode::Leaf(x) => x.gravity_at(pos, use_quad, accuracy),
-            QuadNode::Interior(x) => x.gravity_at(pos, use_quad, accuracy),
+            QuadNode::Leaf(x) => x.gravity_at(pos, use_quad, accuracy, epsilon),
+            QuadNode::Interior(x) => x.gravity_at(pos, use_quad, accuracy, epsilon),
         }
     }
+
+    fn tidal_at(&self, pos: Vec2<T>, use_quad: bool, accuracy: T) -> Mat2<T> {
+        match self {
+            QuadNode::Leaf(x) => x.tidal_at(pos, use_quad, accuracy),
+            QuadNode::Interior(x) => x.tidal_at(pos, use_quad, accuracy),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree_of(bodies: &[(f64, f64, f64)]) -> QuadNode<f64> {
+        let pts = bodies
+            .iter()
+            .enumerate()
+            .map(|(idx, &(x, y, mass))| Body {
+                pos: Vec2::new(x, y),
+                mass,
+                idx,
+            })
+            .collect();
+        tree_from_points(pts)
+    }
+
+    /// Gravity is the gradient of the potential with a sign flip, since it
+    /// points down the potential; check `gravity_at` against a central
+    /// finite difference of `potential_at`.
+    #[test]
+    fn gravity_matches_finite_difference_of_potential() {
+        let root = tree_of(&[(1.0, 2.0, 3.0), (-2.0, 0.5, 1.5), (0.3, -1.7, 2.2)]);
+        let pos = Vec2::new(0.7, -0.4);
+        let (use_quad, accuracy, epsilon) = (true, 1e-9, 0.0);
+        let h = 1e-4;
+        let phi = |d: Vec2<f64>| root.potential_at(pos + d, use_quad, accuracy, epsilon);
+
+        let dphi_dx = (phi(Vec2::new(h, 0.)) - phi(Vec2::new(-h, 0.))) / (2. * h);
+        let dphi_dy = (phi(Vec2::new(0., h)) - phi(Vec2::new(0., -h))) / (2. * h);
+
+        let gravity = root.gravity_at(pos, use_quad, accuracy, epsilon);
+        assert!(
+            (gravity.x - (-dphi_dx)).abs() < 1e-6,
+            "gravity.x = {}, -dphi/dx = {}",
+            gravity.x,
+            -dphi_dx
+        );
+        assert!(
+            (gravity.y - (-dphi_dy)).abs() < 1e-6,
+            "gravity.y = {}, -dphi/dy = {}",
+            gravity.y,
+            -dphi_dy
+        );
+    }
+
+    /// `evaluate_tidal_tensor` is documented as the Hessian of the
+    /// potential; check that against a central finite difference.
+    #[test]
+    fn tidal_tensor_matches_finite_difference_hessian() {
+        let root = tree_of(&[(1.0, 2.0, 3.0), (-2.0, 0.5, 1.5), (0.3, -1.7, 2.2)]);
+        let pos = Vec2::new(0.7, -0.4);
+        let (use_quad, accuracy, epsilon) = (true, 1e-9, 0.0);
+        let h = 1e-3;
+        let phi = |dx: f64, dy: f64| {
+            root.potential_at(pos + Vec2::new(dx, dy), use_quad, accuracy, epsilon)
+        };
+
+        let phi0 = phi(0., 0.);
+        let dxx = (phi(h, 0.) - 2. * phi0 + phi(-h, 0.)) / (h * h);
+        let dyy = (phi(0., h) - 2. * phi0 + phi(0., -h)) / (h * h);
+        let dxy = (phi(h, h) - phi(h, -h) - phi(-h, h) + phi(-h, -h)) / (4. * h * h);
+
+        let hessian = root.tidal_at(pos, use_quad, accuracy);
+        assert!(
+            (hessian.xx - dxx).abs() < 1e-2,
+            "hessian.xx = {}, d2phi/dx2 = {}",
+            hessian.xx,
+            dxx
+        );
+        assert!(
+            (hessian.yy - dyy).abs() < 1e-2,
+            "hessian.yy = {}, d2phi/dy2 = {}",
+            hessian.yy,
+            dyy
+        );
+        assert!(
+            (hessian.xy - dxy).abs() < 1e-2,
+            "hessian.xy = {}, d2phi/dxdy = {}",
+            hessian.xy,
+            dxy
+        );
+    }
 }
@@ -1,4 +1,7 @@
-use std::ops::{Add, Mul};
+use std::{
+    iter::Sum,
+    ops::{Add, Mul},
+};
 
 use crate::vec2::Vec2;
 
@@ -60,3 +63,12 @@ where
         }
     }
 }
+
+impl<T> Sum for Mat2<T>
+where
+    T: Copy + Add<T, Output = T> + Default,
+{
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(Self::add).unwrap_or_default()
+    }
+}
@@ -0,0 +1,75 @@
+use std::ops::{Add, Mul};
+
+use crate::vec3::Vec3;
+
+/// A symmetric 3x3 matrix, stored as its 6 independent components.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mat3<T> {
+    pub xx: T,
+    pub yy: T,
+    pub zz: T,
+    pub xy: T,
+    pub xz: T,
+    pub yz: T,
+}
+
+impl<T> Mat3<T>
+where
+    T: Copy + Mul<T, Output = T> + Add<T, Output = T>,
+{
+    /// Evaluates v^T@self@v
+    pub fn eval_quadratic(&self, v: Vec3<T>) -> T {
+        let Vec3 { x, y, z } = v;
+        self.xx * x * x
+            + self.yy * y * y
+            + self.zz * z * z
+            + (self.xy + self.xy) * x * y
+            + (self.xz + self.xz) * x * z
+            + (self.yz + self.yz) * y * z
+    }
+
+    pub fn matmul(&self, v: Vec3<T>) -> Vec3<T> {
+        let Vec3 { x, y, z } = v;
+        Vec3 {
+            x: self.xx * x + self.xy * y + self.xz * z,
+            y: self.xy * x + self.yy * y + self.yz * z,
+            z: self.xz * x + self.yz * y + self.zz * z,
+        }
+    }
+}
+
+impl<T> Mul<T> for Mat3<T>
+where
+    T: Copy + Mul<T, Output = T>,
+{
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self {
+            xx: self.xx * rhs,
+            yy: self.yy * rhs,
+            zz: self.zz * rhs,
+            xy: self.xy * rhs,
+            xz: self.xz * rhs,
+            yz: self.yz * rhs,
+        }
+    }
+}
+
+impl<T> Add<Self> for Mat3<T>
+where
+    T: Copy + Add<T, Output = T>,
+{
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            xx: self.xx + rhs.xx,
+            yy: self.yy + rhs.yy,
+            zz: self.zz + rhs.zz,
+            xy: self.xy + rhs.xy,
+            xz: self.xz + rhs.xz,
+            yz: self.yz + rhs.yz,
+        }
+    }
+}
@@ -0,0 +1,598 @@
+//! The 3D analogue of the quadtree logic in `lib.rs`: an octree over
+//! [`Vec3`] positions, using the traceless 3D quadrupole moment for the
+//! Barnes-Hut approximation.
+use numpy::{PyArray1, PyArray2, PyReadonlyArrayDyn};
+use pyo3::{exceptions::PyValueError, prelude::*};
+// `rayon` is an optional dependency enabled by the `rayon` feature; both are
+// declared in Cargo.toml, which lives outside this source tree.
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    aabb::Aabb3,
+    mat3::Mat3,
+    scalar::Scalar,
+    utils::{check_pos_array3, to_pos_array3},
+    vec3::Vec3,
+    DEFAULT_ACC,
+};
+
+/// Below this many points, `make_oct_node` builds its eight children serially
+/// rather than paying rayon's task-spawning overhead.
+#[cfg(feature = "rayon")]
+const RAYON_NODE_THRESHOLD: usize = 1024;
+
+#[pyclass]
+struct GravitySystem3D {
+    root: OctNode<f64>,
+    /// Plummer softening length: `potential_at`/`gravity_at` replace `dist`
+    /// with `sqrt(dist^2 + epsilon^2)`, so the field stays finite at
+    /// near-coincident points instead of blowing up as `dist -> 0`. With the
+    /// default `epsilon = 0`, exactly coincident points (e.g. a body queried
+    /// at its own position) still fall back to the old, unsoftened `0`
+    /// rather than producing `inf`/`NaN`.
+    epsilon: f64,
+}
+#[pymethods]
+impl GravitySystem3D {
+    /// `epsilon` is a Plummer softening length added in quadrature to `dist`
+    /// in the force/potential formulas, keeping them finite for
+    /// near-coincident points. It defaults to `0`, under which querying
+    /// exactly at a body's own position still yields the old finite `0`
+    /// rather than `inf`/`NaN` — pass a positive `epsilon` to get smooth,
+    /// non-singular self-gravity instead.
+    #[new]
+    #[pyo3(signature = (positions, masses, epsilon=0.))]
+    fn py_new(
+        positions: PyReadonlyArrayDyn<f64>,
+        masses: PyReadonlyArrayDyn<f64>,
+        epsilon: f64,
+    ) -> PyResult<Self> {
+        let n = *positions.shape().first().unwrap_or(&0);
+        if n != *masses.shape().first().unwrap_or(&0) {
+            return Err(PyValueError::new_err(format!(
+                "The sizes of the positions and masses arrays should be equal; were {} and {}",
+                n,
+                masses.len()
+            )));
+        }
+        if n == 0 {
+            return Err(PyValueError::new_err("The number of points can't be zero."));
+        }
+        if masses.shape() != [n] {
+            return Err(PyValueError::new_err(format!(
+                "The masses array should be 1d, got shape {:?}.",
+                masses.shape(),
+            )));
+        }
+        let positions = positions.as_array();
+        let masses = masses.as_array();
+        let vecs = check_pos_array3(&positions)?;
+        let pts = vecs
+            .zip(masses.iter())
+            .map(|(pos, m)| Body3 { pos, mass: *m })
+            .collect();
+        Ok(Self {
+            root: octree_from_points(pts),
+            epsilon,
+        })
+    }
+
+    #[pyo3(signature = (at_pos, use_quad=true, accuracy=DEFAULT_ACC))]
+    fn evaluate_potential<'py>(
+        &self,
+        py: Python<'py>,
+        at_pos: PyReadonlyArrayDyn<f64>,
+        use_quad: bool,
+        accuracy: f64,
+    ) -> PyResult<&'py PyArray1<f64>> {
+        let arr = at_pos.as_array();
+        let vecs: Vec<_> = check_pos_array3(&arr)?.collect();
+        let result = py.allow_threads(|| {
+            eval_potential_parallel(&self.root, &vecs, use_quad, accuracy, self.epsilon)
+        });
+        Ok(PyArray1::from_vec(py, result))
+    }
+
+    #[pyo3(signature = (at_pos, use_quad=true, accuracy=DEFAULT_ACC))]
+    fn evaluate_gravity<'py>(
+        &self,
+        py: Python<'py>,
+        at_pos: PyReadonlyArrayDyn<f64>,
+        use_quad: bool,
+        accuracy: f64,
+    ) -> PyResult<&'py PyArray2<f64>> {
+        let arr = at_pos.as_array();
+        let vecs: Vec<_> = check_pos_array3(&arr)?.collect();
+        let result = py.allow_threads(|| {
+            eval_gravity_parallel(&self.root, &vecs, use_quad, accuracy, self.epsilon)
+        });
+        Ok(to_pos_array3(py, result.into_iter()))
+    }
+}
+
+pub(crate) fn register(m: &PyModule) -> PyResult<()> {
+    m.add_class::<GravitySystem3D>()
+}
+
+/// Evaluates the potential at every one of `vecs`, over a rayon `par_iter`
+/// when the `rayon` feature is on, else a plain sequential iterator.
+fn eval_potential_parallel<T: Scalar>(
+    root: &OctNode<T>,
+    vecs: &[Vec3<T>],
+    use_quad: bool,
+    accuracy: T,
+    epsilon: T,
+) -> Vec<T> {
+    #[cfg(feature = "rayon")]
+    {
+        vecs.par_iter()
+            .map(|&v| root.potential_at(v, use_quad, accuracy, epsilon))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        vecs.iter()
+            .map(|&v| root.potential_at(v, use_quad, accuracy, epsilon))
+            .collect()
+    }
+}
+
+/// Evaluates gravity at every one of `vecs`; see [`eval_potential_parallel`].
+fn eval_gravity_parallel<T: Scalar>(
+    root: &OctNode<T>,
+    vecs: &[Vec3<T>],
+    use_quad: bool,
+    accuracy: T,
+    epsilon: T,
+) -> Vec<Vec3<T>> {
+    #[cfg(feature = "rayon")]
+    {
+        vecs.par_iter()
+            .map(|&v| root.gravity_at(v, use_quad, accuracy, epsilon))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        vecs.iter()
+            .map(|&v| root.gravity_at(v, use_quad, accuracy, epsilon))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Body3<T: Scalar> {
+    mass: T,
+    pos: Vec3<T>,
+}
+
+trait Octant<T: Scalar>: Sized {
+    fn com(&self) -> (T, Vec3<T>);
+    fn quadrupole(&self) -> Mat3<T>;
+    fn potential_at(&self, pos: Vec3<T>, use_quad: bool, accuracy: T, epsilon: T) -> T;
+    fn gravity_at(&self, pos: Vec3<T>, use_quad: bool, accuracy: T, epsilon: T) -> Vec3<T>;
+}
+
+enum OctNode<T: Scalar> {
+    Leaf(OctLeaf<T>),
+    Interior(OctInterior<T>),
+}
+
+struct OctLeaf<T: Scalar> {
+    body: Body3<T>,
+}
+struct OctInterior<T: Scalar> {
+    /// zyx bit pattern: bit 0 is x, bit 1 is y, bit 2 is z; 0 is the lower
+    /// half of that axis, 1 is the upper half.
+    children: [Option<Box<OctNode<T>>>; 8],
+    com: Vec3<T>,
+    total_mass: T,
+    quadrupole: Mat3<T>,
+
+    aabb: Aabb3<T>,
+}
+impl<T: Scalar> OctInterior<T> {
+    fn new(children: [Option<OctNode<T>>; 8], aabb: Aabb3<T>) -> Self {
+        let (total_mass, com) = {
+            let mut mass = T::default();
+            let mut com = Vec3::zero();
+            for child in children.iter().flatten() {
+                let (child_m, child_com) = child.com();
+                mass = mass + child_m;
+                com = com + child_com * child_m;
+            }
+            assert!(mass != T::default()); // sanity check
+            com = com / mass;
+            (mass, com)
+        };
+        let mut quadrupole = Mat3::default();
+        for child in children.iter().flatten() {
+            let (child_m, child_com) = child.com();
+            let child_q = child.quadrupole();
+            quadrupole = quadrupole + child_q + to_quadrup_tensor3(com - child_com) * child_m;
+        }
+        Self {
+            children: children.map(|c| c.map(Box::new)),
+            total_mass,
+            com,
+            quadrupole,
+            aabb,
+        }
+    }
+
+    fn width(&self) -> T {
+        (self.aabb.max - self.aabb.min).norm()
+    }
+
+    fn some_children(&self) -> impl Iterator<Item = &OctNode<T>> {
+        self.children
+            .iter()
+            .filter_map(|x| x.as_ref().map(|n| n.as_ref()))
+    }
+}
+
+fn make_oct_node<T: Scalar>(pts: Vec<Body3<T>>, aabb: Aabb3<T>) -> Option<OctNode<T>> {
+    if pts.is_empty() {
+        None
+    } else if pts.len() == 1 {
+        Some(OctNode::Leaf(OctLeaf {
+            body: *pts.first().unwrap(),
+        }))
+    } else {
+        let Vec3 {
+            x: lx,
+            y: ly,
+            z: lz,
+        } = aabb.min;
+        let Vec3 {
+            x: hx,
+            y: hy,
+            z: hz,
+        } = aabb.max;
+        let half = T::from_f64(0.5);
+        let mx = (lx + hx) * half;
+        let my = (ly + hy) * half;
+        let mz = (lz + hz) * half;
+
+        let split = |x_hi: bool, y_hi: bool, z_hi: bool| -> (Vec<Body3<T>>, Aabb3<T>) {
+            let sub_pts = pts
+                .iter()
+                .copied()
+                .filter(|b| {
+                    (b.pos.x >= mx) == x_hi && (b.pos.y >= my) == y_hi && (b.pos.z >= mz) == z_hi
+                })
+                .collect();
+            let sub_aabb = Aabb3::new(
+                Vec3::new(
+                    if x_hi { mx } else { lx },
+                    if y_hi { my } else { ly },
+                    if z_hi { mz } else { lz },
+                ),
+                Vec3::new(
+                    if x_hi { hx } else { mx },
+                    if y_hi { hy } else { my },
+                    if z_hi { hz } else { mz },
+                ),
+            );
+            (sub_pts, sub_aabb)
+        };
+
+        // zyx bit pattern, matching `OctInterior::children`.
+        let (o0_pts, o0_aabb) = split(false, false, false);
+        let (o1_pts, o1_aabb) = split(true, false, false);
+        let (o2_pts, o2_aabb) = split(false, true, false);
+        let (o3_pts, o3_aabb) = split(true, true, false);
+        let (o4_pts, o4_aabb) = split(false, false, true);
+        let (o5_pts, o5_aabb) = split(true, false, true);
+        let (o6_pts, o6_aabb) = split(false, true, true);
+        let (o7_pts, o7_aabb) = split(true, true, true);
+
+        #[cfg(feature = "rayon")]
+        let children = if pts.len() >= RAYON_NODE_THRESHOLD {
+            let (((c0, c1), (c2, c3)), ((c4, c5), (c6, c7))) = rayon::join(
+                || {
+                    rayon::join(
+                        || {
+                            rayon::join(
+                                || make_oct_node(o0_pts, o0_aabb),
+                                || make_oct_node(o1_pts, o1_aabb),
+                            )
+                        },
+                        || {
+                            rayon::join(
+                                || make_oct_node(o2_pts, o2_aabb),
+                                || make_oct_node(o3_pts, o3_aabb),
+                            )
+                        },
+                    )
+                },
+                || {
+                    rayon::join(
+                        || {
+                            rayon::join(
+                                || make_oct_node(o4_pts, o4_aabb),
+                                || make_oct_node(o5_pts, o5_aabb),
+                            )
+                        },
+                        || {
+                            rayon::join(
+                                || make_oct_node(o6_pts, o6_aabb),
+                                || make_oct_node(o7_pts, o7_aabb),
+                            )
+                        },
+                    )
+                },
+            );
+            [c0, c1, c2, c3, c4, c5, c6, c7]
+        } else {
+            [
+                make_oct_node(o0_pts, o0_aabb),
+                make_oct_node(o1_pts, o1_aabb),
+                make_oct_node(o2_pts, o2_aabb),
+                make_oct_node(o3_pts, o3_aabb),
+                make_oct_node(o4_pts, o4_aabb),
+                make_oct_node(o5_pts, o5_aabb),
+                make_oct_node(o6_pts, o6_aabb),
+                make_oct_node(o7_pts, o7_aabb),
+            ]
+        };
+        #[cfg(not(feature = "rayon"))]
+        let children = [
+            make_oct_node(o0_pts, o0_aabb),
+            make_oct_node(o1_pts, o1_aabb),
+            make_oct_node(o2_pts, o2_aabb),
+            make_oct_node(o3_pts, o3_aabb),
+            make_oct_node(o4_pts, o4_aabb),
+            make_oct_node(o5_pts, o5_aabb),
+            make_oct_node(o6_pts, o6_aabb),
+            make_oct_node(o7_pts, o7_aabb),
+        ];
+
+        Some(OctNode::Interior(OctInterior::new(children, aabb)))
+    }
+}
+
+fn octree_from_points<T: Scalar>(pts: Vec<Body3<T>>) -> OctNode<T> {
+    assert!(!pts.is_empty());
+    let aabb = Aabb3::from_points(pts.iter().map(|b| b.pos)).unwrap();
+    make_oct_node(pts, aabb).unwrap()
+}
+
+/// Computes the traceless 3D quadrupole moment Q_{αβ} = 3 r_α r_β - δ_{αβ} r²
+fn to_quadrup_tensor3<T: Scalar>(r: Vec3<T>) -> Mat3<T> {
+    let r2 = r.x * r.x + r.y * r.y + r.z * r.z;
+    let three = T::from_f64(3.);
+    Mat3 {
+        xx: three * r.x * r.x - r2,
+        yy: three * r.y * r.y - r2,
+        zz: three * r.z * r.z - r2,
+        xy: three * r.x * r.y,
+        xz: three * r.x * r.z,
+        yz: three * r.y * r.z,
+    }
+}
+
+impl<T: Scalar> Octant<T> for OctLeaf<T> {
+    fn com(&self) -> (T, Vec3<T>) {
+        (self.body.mass, self.body.pos)
+    }
+
+    fn quadrupole(&self) -> Mat3<T> {
+        Mat3::default()
+    }
+
+    fn potential_at(&self, pos: Vec3<T>, _use_quad: bool, _accuracy: T, epsilon: T) -> T {
+        let r = pos - self.body.pos;
+        let soft_dist = (r.sq_len() + epsilon * epsilon).sqrt();
+        if soft_dist == T::default() {
+            // unsoftened (epsilon=0) self-interaction: keep the old finite
+            // result instead of dividing by zero.
+            T::default()
+        } else {
+            T::G * self.body.mass / soft_dist
+        }
+    }
+
+    fn gravity_at(&self, pos: Vec3<T>, _use_quad: bool, _accuracy: T, epsilon: T) -> Vec3<T> {
+        let r = pos - self.body.pos;
+        let soft_dist = (r.sq_len() + epsilon * epsilon).sqrt();
+        if soft_dist == T::default() {
+            // unsoftened (epsilon=0) self-interaction: keep the old finite
+            // result instead of dividing by zero.
+            Default::default()
+        } else {
+            r * (T::G * self.body.mass / soft_dist.powi(3))
+        }
+    }
+}
+
+impl<T: Scalar> Octant<T> for OctInterior<T> {
+    fn com(&self) -> (T, Vec3<T>) {
+        (self.total_mass, self.com)
+    }
+
+    fn quadrupole(&self) -> Mat3<T> {
+        self.quadrupole
+    }
+
+    fn potential_at(&self, pos: Vec3<T>, use_quad: bool, accuracy: T, epsilon: T) -> T {
+        let (mass, com) = self.com();
+        let r = pos - com;
+        let dist = r.norm();
+        if dist > T::default() && self.width() / dist < accuracy {
+            let soft_dist = (r.sq_len() + epsilon * epsilon).sqrt();
+            let scalar_part = mass / soft_dist;
+            let mut total = scalar_part;
+            if use_quad {
+                let e = r / dist;
+                let quadrupole_part =
+                    self.quadrupole().eval_quadratic(e) / (T::from_f64(2.) * soft_dist.powi(3));
+                total = total + quadrupole_part;
+            }
+            T::G * total
+        } else {
+            // exact calculation
+            self.some_children()
+                .map(|x| x.potential_at(pos, use_quad, accuracy, epsilon))
+                .sum::<T>()
+        }
+    }
+
+    fn gravity_at(&self, pos: Vec3<T>, use_quad: bool, accuracy: T, epsilon: T) -> Vec3<T> {
+        let (mass, com) = self.com();
+        let r = pos - com;
+        let dist = r.norm();
+        if dist > T::default() && self.width() / dist < accuracy {
+            let soft_dist = (r.sq_len() + epsilon * epsilon).sqrt();
+            let scalar_part = r * (mass / soft_dist.powi(3));
+            let mut total = scalar_part;
+            if use_quad {
+                let e = r / dist;
+                let dist4 = soft_dist.powi(4);
+                let quadrupole_part_1 =
+                    e * (self.quadrupole().eval_quadratic(e) * T::from_f64(2.5) / dist4);
+                let quadrupole_part_2 = -self.quadrupole().matmul(e) / dist4;
+                total = total + quadrupole_part_1 + quadrupole_part_2;
+            }
+            total * T::G
+        } else {
+            // exact calculation
+            self.some_children()
+                .map(|x| x.gravity_at(pos, use_quad, accuracy, epsilon))
+                .sum()
+        }
+    }
+}
+
+impl<T: Scalar> Octant<T> for OctNode<T> {
+    fn com(&self) -> (T, Vec3<T>) {
+        match self {
+            OctNode::Leaf(x) => x.com(),
+            OctNode::Interior(x) => x.com(),
+        }
+    }
+
+    fn quadrupole(&self) -> Mat3<T> {
+        match self {
+            OctNode::Leaf(x) => x.quadrupole(),
+            OctNode::Interior(x) => x.quadrupole(),
+        }
+    }
+
+    fn potential_at(&self, pos: Vec3<T>, use_quad: bool, accuracy: T, epsilon: T) -> T {
+        match self {
+            OctNode::Leaf(x) => x.potential_at(pos, use_quad, accuracy, epsilon),
+            OctNode::Interior(x) => x.potential_at(pos, use_quad, accuracy, epsilon),
+        }
+    }
+
+    fn gravity_at(&self, pos: Vec3<T>, use_quad: bool, accuracy: T, epsilon: T) -> Vec3<T> {
+        match self {
+            OctNode::Leaf(x) => x.gravity_at(pos, use_quad, accuracy, epsilon),
+            OctNode::Interior(x) => x.gravity_at(pos, use_quad, accuracy, epsilon),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_octree(bodies: &[(f64, f64, f64, f64)]) -> OctNode<f64> {
+        let pts = bodies
+            .iter()
+            .map(|&(x, y, z, mass)| Body3 {
+                pos: Vec3::new(x, y, z),
+                mass,
+            })
+            .collect();
+        octree_from_points(pts)
+    }
+
+    fn direct_potential(bodies: &[(f64, f64, f64, f64)], pos: Vec3<f64>) -> f64 {
+        bodies
+            .iter()
+            .map(|&(x, y, z, mass)| {
+                let dist = (pos - Vec3::new(x, y, z)).norm();
+                <f64 as Scalar>::G * mass / dist
+            })
+            .sum()
+    }
+
+    fn direct_gravity(bodies: &[(f64, f64, f64, f64)], pos: Vec3<f64>) -> Vec3<f64> {
+        bodies
+            .iter()
+            .map(|&(x, y, z, mass)| {
+                let r = pos - Vec3::new(x, y, z);
+                let dist = r.norm();
+                r * (<f64 as Scalar>::G * mass / dist.powi(3))
+            })
+            .sum()
+    }
+
+    /// With an `accuracy` tight enough to force exact (leaf-level)
+    /// evaluation everywhere, the octree's `potential_at`/`gravity_at` must
+    /// reproduce direct O(n^2) summation exactly, regardless of the `zyx`
+    /// child-octant each body lands in.
+    #[test]
+    fn octree_matches_direct_sum_when_forced_exact() {
+        let bodies = [
+            (1.0, 2.0, -1.5, 3.0),
+            (-2.0, 0.5, 1.0, 1.5),
+            (0.3, -1.7, 2.2, 2.2),
+            (-1.1, -0.8, -0.3, 1.1),
+            (2.4, 1.3, 0.7, 0.8),
+        ];
+        let root = make_octree(&bodies);
+        let pos = Vec3::new(0.7, -0.4, 1.2);
+        let (use_quad, accuracy, epsilon) = (true, 1e-9, 0.0);
+
+        let potential = root.potential_at(pos, use_quad, accuracy, epsilon);
+        let expected_potential = direct_potential(&bodies, pos);
+        assert!(
+            (potential - expected_potential).abs() < 1e-9,
+            "potential = {potential}, direct sum = {expected_potential}"
+        );
+
+        let gravity = root.gravity_at(pos, use_quad, accuracy, epsilon);
+        let expected_gravity = direct_gravity(&bodies, pos);
+        assert!(
+            (gravity - expected_gravity).norm::<f64>() < 1e-9,
+            "gravity = {gravity:?}, direct sum = {expected_gravity:?}"
+        );
+    }
+
+    /// With a query point far outside an asymmetric cluster (so the
+    /// quadrupole moment is non-zero), the quadrupole-enabled approximation
+    /// should still land close to the direct O(n^2) sum, exercising
+    /// `to_quadrup_tensor3` and the parallel-axis shift in
+    /// `OctInterior::new`.
+    #[test]
+    fn octree_quadrupole_approximation_is_close_to_direct_sum() {
+        let bodies = [
+            (1.0, 0.0, 0.0, 3.0),
+            (-0.5, 0.8, 0.0, 1.0),
+            (0.0, -0.6, 0.4, 2.0),
+            (-0.3, 0.2, -0.7, 1.5),
+        ];
+        let root = make_octree(&bodies);
+        let pos = Vec3::new(40.0, -30.0, 20.0);
+        let (use_quad, accuracy, epsilon) = (true, DEFAULT_ACC, 0.0);
+
+        let potential = root.potential_at(pos, use_quad, accuracy, epsilon);
+        let expected_potential = direct_potential(&bodies, pos);
+        let rel_err = ((potential - expected_potential) / expected_potential).abs();
+        assert!(
+            rel_err < 1e-2,
+            "potential = {potential}, direct sum = {expected_potential}, rel_err = {rel_err}"
+        );
+
+        let gravity = root.gravity_at(pos, use_quad, accuracy, epsilon);
+        let expected_gravity = direct_gravity(&bodies, pos);
+        let rel_err = (gravity - expected_gravity).norm::<f64>() / expected_gravity.norm::<f64>();
+        assert!(
+            rel_err < 1e-2,
+            "gravity = {gravity:?}, direct sum = {expected_gravity:?}, rel_err = {rel_err}"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! A bound that lets the quadtree and its force evaluation be written once
+//! and instantiated for both `f32` and `f64`, the way nalgebra/cgmath
+//! generalize over their scalar type.
+use std::{
+    iter::Sum,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use crate::vec2::Sqrt;
+
+/// Arithmetic + the handful of named constants and float-only functions
+/// (`hypot`, `powi`) that [`crate::QuadNode`] and friends need.
+/// Also requires [`numpy::Element`] since values of this type are what
+/// actually cross the numpy/Python boundary.
+pub trait Scalar:
+    Copy
+    + Default
+    + PartialOrd
+    + Sqrt
+    + Sum
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + std::fmt::Debug
+    + numpy::Element
+    + Send
+    + Sync
+    + 'static
+{
+    /// The gravitational constant. Negative, so that `gravity_at` returns an
+    /// attractive acceleration pointing from the query point towards the mass.
+    const G: Self;
+
+    fn hypot(self, other: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    /// Convert a literal constant (e.g. `2.5`) into `Self`.
+    fn from_f64(v: f64) -> Self;
+}
+
+impl Scalar for f32 {
+    const G: Self = -1.;
+
+    #[inline]
+    fn hypot(self, other: Self) -> Self {
+        f32::hypot(self, other)
+    }
+
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        f32::powi(self, n)
+    }
+
+    #[inline]
+    fn from_f64(v: f64) -> Self {
+        v as Self
+    }
+}
+
+impl Scalar for f64 {
+    const G: Self = -1.;
+
+    #[inline]
+    fn hypot(self, other: Self) -> Self {
+        f64::hypot(self, other)
+    }
+
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        f64::powi(self, n)
+    }
+
+    #[inline]
+    fn from_f64(v: f64) -> Self {
+        v as Self
+    }
+}
@@ -1,14 +1,14 @@
 use numpy::{
     ndarray::{Array, ArrayView, Dimension},
-    PyArray2,
+    PyArray2, PyArray3,
 };
 use pyo3::{exceptions::PyValueError, PyResult, Python};
 
-use crate::{vec2::Vec2, F};
+use crate::{mat2::Mat2, scalar::Scalar, vec2::Vec2, vec3::Vec3};
 
-pub fn check_pos_array<'a, D: Dimension>(
-    arr: &'a ArrayView<F, D>,
-) -> PyResult<impl Iterator<Item = Vec2<F>> + 'a> {
+pub fn check_pos_array<'a, T: Scalar, D: Dimension>(
+    arr: &'a ArrayView<T, D>,
+) -> PyResult<impl Iterator<Item = Vec2<T>> + 'a> {
     let shape = arr.shape();
     match shape {
         &[_n, 2] => Ok(arr.rows().into_iter().map(|row| Vec2 {
@@ -26,10 +26,57 @@ pub fn check_pos_array<'a, D: Dimension>(
     }
 }
 
-pub fn to_pos_array(py: Python<'_>, positions: impl Iterator<Item = Vec2<F>>) -> &'_ PyArray2<F> {
+pub fn to_pos_array<T: Scalar>(
+    py: Python<'_>,
+    positions: impl Iterator<Item = Vec2<T>>,
+) -> &'_ PyArray2<T> {
     let arr = positions
         .flat_map(|v| [v.x, v.y].into_iter())
         .collect::<Vec<_>>();
     let n = arr.len() / 2;
     PyArray2::from_owned_array(py, Array::from_shape_vec([n, 2], arr).unwrap())
 }
+
+pub fn check_pos_array3<'a, T: Scalar, D: Dimension>(
+    arr: &'a ArrayView<T, D>,
+) -> PyResult<impl Iterator<Item = Vec3<T>> + 'a> {
+    let shape = arr.shape();
+    match shape {
+        &[_n, 3] => Ok(arr.rows().into_iter().map(|row| Vec3 {
+            x: row[0],
+            y: row[1],
+            z: row[2],
+        })),
+        [_n, _m] => Err(PyValueError::new_err(format!(
+            "Array must be of shape (n,3) but was {:?}",
+            shape
+        ))),
+        _ => Err(PyValueError::new_err(format!(
+            "Array must be two-dimensional but was of shape {:?}",
+            shape
+        ))),
+    }
+}
+
+/// Packs a sequence of symmetric 2x2 tensors into an `(n, 2, 2)` numpy array.
+pub fn to_tensor_array<T: Scalar>(
+    py: Python<'_>,
+    tensors: impl Iterator<Item = Mat2<T>>,
+) -> &'_ PyArray3<T> {
+    let arr = tensors
+        .flat_map(|m| [m.xx, m.xy, m.yx, m.yy].into_iter())
+        .collect::<Vec<_>>();
+    let n = arr.len() / 4;
+    PyArray3::from_owned_array(py, Array::from_shape_vec([n, 2, 2], arr).unwrap())
+}
+
+pub fn to_pos_array3<T: Scalar>(
+    py: Python<'_>,
+    positions: impl Iterator<Item = Vec3<T>>,
+) -> &'_ PyArray2<T> {
+    let arr = positions
+        .flat_map(|v| [v.x, v.y, v.z].into_iter())
+        .collect::<Vec<_>>();
+    let n = arr.len() / 3;
+    PyArray2::from_owned_array(py, Array::from_shape_vec([n, 3], arr).unwrap())
+}
@@ -4,8 +4,6 @@ use std::{
     ops::{Add, Div, Mul, Neg, Sub},
 };
 
-use crate::F;
-
 #[derive(Clone, Copy, Debug, Default)]
 pub struct Vec2<T>
 where
@@ -146,7 +144,7 @@ impl<T: Copy> Vec2<T> {
 
 impl<T> Vec2<T>
 where
-    T: Copy + std::ops::Div<Output = F>,
+    T: Copy,
 {
     /// Normalize vector by dividing by the norm.
     #[inline]
@@ -0,0 +1,162 @@
+#![allow(dead_code)]
+use std::{
+    iter::Sum,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use crate::vec2::Sqrt;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Vec3<T>
+where
+    T: Copy,
+{
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+impl<T: Add<Output = T> + Copy> Add for Vec3<T> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Vec3 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+impl<T: Sub<Output = T> + Copy> Sub for Vec3<T> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Vec3 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+impl<T, S> Mul<S> for Vec3<T>
+where
+    T: Mul<S, Output = T> + Copy,
+    S: Copy,
+{
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: S) -> Self::Output {
+        Vec3 {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
+}
+impl<T, S, R> Div<S> for Vec3<T>
+where
+    T: Div<S, Output = R> + Copy,
+    S: Copy,
+    R: Copy,
+{
+    type Output = Vec3<R>;
+
+    #[inline]
+    fn div(self, rhs: S) -> Self::Output {
+        Vec3 {
+            x: self.x / rhs,
+            y: self.y / rhs,
+            z: self.z / rhs,
+        }
+    }
+}
+impl<T: Neg<Output = T> + Copy> Neg for Vec3<T> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Vec3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+#[allow(dead_code)]
+impl<T: Copy> Vec3<T> {
+    #[inline]
+    pub fn new(x: T, y: T, z: T) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    /// A vector of Default values for the type.
+    #[inline]
+    pub fn zero() -> Self
+    where
+        T: Default,
+    {
+        Self {
+            x: Default::default(),
+            y: Default::default(),
+            z: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn dot<H, R>(self, other: Vec3<H>) -> R
+    where
+        T: Mul<H, Output = R>,
+        R: Add<R, Output = R>,
+        H: Copy,
+    {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    #[inline]
+    pub fn cross(self, other: Self) -> Self
+    where
+        T: Mul<T, Output = T> + Sub<T, Output = T>,
+    {
+        Vec3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    /// Squared length
+    #[inline]
+    pub fn sq_len<R>(self) -> R
+    where
+        T: Mul<T, Output = R>,
+        R: Add<R, Output = R>,
+    {
+        self.x * self.x + self.y * self.y + self.z * self.z
+    }
+
+    /// Vector length (Euclidean norm).
+    #[inline]
+    pub fn norm<R>(self) -> R
+    where
+        T: Mul<T, Output = R>,
+        R: Add<R, Output = R> + Sqrt,
+    {
+        self.sq_len().sqrt()
+    }
+
+    #[inline]
+    pub fn as_tuple(&self) -> (T, T, T) {
+        (self.x, self.y, self.z)
+    }
+}
+
+impl<T> Sum for Vec3<T>
+where
+    T: Copy + Add<T, Output = T> + Default,
+{
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(Self::add).unwrap_or_default()
+    }
+}